@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use filter::Filter;
+
+/// The commit message style to parse against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitStyle {
+    /// The classic git-journal `[Category]` bracket syntax
+    GitJournal,
+    /// Conventional Commits, e.g. `feat(scope)!: subject`
+    Conventional,
+}
+
+impl Default for CommitStyle {
+    fn default() -> CommitStyle {
+        CommitStyle::GitJournal
+    }
+}
+
+/// The output format a `Renderer` produces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored output for an interactive terminal (the default)
+    Terminal,
+    /// Markdown, suitable for committing as `CHANGELOG.md`
+    Markdown,
+    /// JSON, for downstream tooling/CI
+    Json,
+}
+
+impl Default for OutputFormat {
+    fn default() -> OutputFormat {
+        OutputFormat::Terminal
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub colored_output: bool,
+    pub show_prefix: bool,
+
+    /// Compiled filter query selecting which entries are printed, e.g.
+    /// `category == "Fixed" && !tag("wip")`. `None` prints everything.
+    pub filter: Option<Filter>,
+
+    /// Which summary grammar to parse commit messages with
+    pub commit_style: CommitStyle,
+
+    /// Maps a Conventional Commits `type` (`feat`, `fix`, ...) to the
+    /// display category used in the changelog (`Added`, `Fixed`, ...).
+    /// Only consulted when `commit_style` is `Conventional`.
+    pub conventional_commit_types: HashMap<String, String>,
+
+    /// Which `Renderer` to print parsed tags/commits with
+    pub output_format: OutputFormat,
+
+    /// Group entries of a tag into one section per category instead of
+    /// printing them in commit order
+    pub group_by_category: bool,
+
+    /// The order in which categories are printed when `group_by_category`
+    /// is enabled. Categories not listed here are appended alphabetically.
+    pub category_order: Vec<String>,
+
+    /// Sort entries within a category by their issue/commit prefix instead
+    /// of alphabetically by text
+    pub sort_by_prefix: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let mut conventional_commit_types = HashMap::new();
+        conventional_commit_types.insert("feat".to_owned(), "Added".to_owned());
+        conventional_commit_types.insert("fix".to_owned(), "Fixed".to_owned());
+        conventional_commit_types.insert("docs".to_owned(), "Changed".to_owned());
+        conventional_commit_types.insert("perf".to_owned(), "Improved".to_owned());
+        conventional_commit_types.insert("refactor".to_owned(), "Changed".to_owned());
+
+        Config {
+            colored_output: true,
+            show_prefix: false,
+            filter: None,
+            commit_style: CommitStyle::GitJournal,
+            conventional_commit_types,
+            output_format: OutputFormat::Terminal,
+            group_by_category: false,
+            category_order: vec!["Added".to_owned(),
+                                  "Changed".to_owned(),
+                                  "Fixed".to_owned(),
+                                  "Improved".to_owned(),
+                                  "Removed".to_owned()],
+            sort_by_prefix: false,
+        }
+    }
+}
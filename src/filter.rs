@@ -0,0 +1,263 @@
+//! A small boolean query language for selecting which changelog entries are
+//! printed, e.g. `category == "Fixed" && !tag("wip")` or `tag("security") || breaking`.
+
+use nom::{IResult, space};
+use regex::Regex;
+
+use std::str;
+
+use parser::{Error, FooterElement, ParagraphElement, ParsedCommit, ListElement, SummaryElement};
+
+/// Anything a `Filter` can be evaluated against
+pub trait Filterable {
+    fn category(&self) -> &str;
+    fn text(&self) -> &str;
+    fn tags(&self) -> &[String];
+
+    fn prefix(&self) -> &str {
+        ""
+    }
+
+    fn footers(&self) -> &[FooterElement] {
+        &[]
+    }
+
+    fn breaking(&self) -> bool {
+        false
+    }
+}
+
+impl Filterable for SummaryElement {
+    fn category(&self) -> &str {
+        &self.category
+    }
+    fn text(&self) -> &str {
+        &self.text
+    }
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+    fn prefix(&self) -> &str {
+        &self.prefix
+    }
+    fn breaking(&self) -> bool {
+        self.breaking
+    }
+}
+
+impl Filterable for ListElement {
+    fn category(&self) -> &str {
+        &self.category
+    }
+    fn text(&self) -> &str {
+        &self.text
+    }
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+impl Filterable for ParagraphElement {
+    fn category(&self) -> &str {
+        ""
+    }
+    fn text(&self) -> &str {
+        &self.text
+    }
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+impl Filterable for ParsedCommit {
+    fn category(&self) -> &str {
+        self.summary.category()
+    }
+    fn text(&self) -> &str {
+        self.summary.text()
+    }
+    fn tags(&self) -> &[String] {
+        self.summary.tags()
+    }
+    fn prefix(&self) -> &str {
+        self.summary.prefix()
+    }
+    fn footers(&self) -> &[FooterElement] {
+        &self.footer
+    }
+    fn breaking(&self) -> bool {
+        self.summary.breaking()
+    }
+}
+
+/// The parsed query AST
+#[derive(Debug, Clone)]
+pub enum Filter {
+    Category(String),
+    Tag(String),
+    Footer(String),
+    /// Holds a pre-compiled `Regex` so `matches` doesn't recompile the
+    /// pattern on every single entry it's evaluated against
+    PrefixMatches(Regex),
+    Breaking,
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    /// Parses a filter expression, e.g. `category == "Fixed" && !tag("wip")`
+    pub fn parse(input: &str) -> Result<Filter, Error> {
+        match parse_or(input.trim().as_bytes()) {
+            IResult::Done(rest, filter) => {
+                if rest.is_empty() {
+                    Ok(filter)
+                } else {
+                    Err(Error::FilterParsing(input.to_owned()))
+                }
+            }
+            _ => Err(Error::FilterParsing(input.to_owned())),
+        }
+    }
+
+    /// Evaluates the filter against a single entry
+    pub fn matches<T: Filterable>(&self, item: &T) -> bool {
+        match *self {
+            Filter::Category(ref category) => item.category() == category,
+            Filter::Tag(ref tag) => item.tags().iter().any(|x| x == tag),
+            Filter::Footer(ref key) => item.footers().iter().any(|f| &f.key == key),
+            Filter::PrefixMatches(ref re) => re.is_match(item.prefix()),
+            Filter::Breaking => item.breaking(),
+            Filter::Not(ref inner) => !inner.matches(item),
+            Filter::And(ref lhs, ref rhs) => lhs.matches(item) && rhs.matches(item),
+            Filter::Or(ref lhs, ref rhs) => lhs.matches(item) || rhs.matches(item),
+        }
+    }
+}
+
+named!(parse_quoted_string<&str>,
+    delimited!(
+        char!('"'),
+        map_res!(take_until!("\""), str::from_utf8),
+        char!('"')
+    )
+);
+
+named!(parse_category_eq<Filter>,
+    chain!(
+        tag!("category") ~ space? ~ tag!("==") ~ space? ~ s: parse_quoted_string,
+        || Filter::Category(s.to_owned())
+    )
+);
+
+named!(parse_tag_call<Filter>,
+    chain!(
+        tag!("tag") ~ space? ~ tag!("(") ~ space? ~ s: parse_quoted_string ~ space? ~ tag!(")"),
+        || Filter::Tag(s.to_owned())
+    )
+);
+
+named!(parse_footer_call<Filter>,
+    chain!(
+        tag!("footer") ~ space? ~ tag!("(") ~ space? ~ s: parse_quoted_string ~ space? ~ tag!(")"),
+        || Filter::Footer(s.to_owned())
+    )
+);
+
+named!(parse_prefix_match<Filter>,
+    chain!(
+        tag!("prefix") ~ space? ~ tag!("~") ~ space? ~ re: map_res!(parse_quoted_string, Regex::new),
+        || Filter::PrefixMatches(re)
+    )
+);
+
+named!(parse_breaking<Filter>,
+    map!(tag!("breaking"), |_| Filter::Breaking)
+);
+
+named!(parse_not<Filter>,
+    chain!(
+        tag!("!") ~ space? ~ f: parse_atom,
+        || Filter::Not(Box::new(f))
+    )
+);
+
+named!(parse_paren<Filter>,
+    chain!(
+        tag!("(") ~ space? ~ f: parse_or ~ space? ~ tag!(")"),
+        || f
+    )
+);
+
+named!(parse_atom<Filter>,
+    alt!(
+        parse_paren |
+        parse_not |
+        parse_category_eq |
+        parse_prefix_match |
+        parse_tag_call |
+        parse_footer_call |
+        parse_breaking
+    )
+);
+
+named!(parse_and<Filter>,
+    chain!(
+        first: parse_atom ~
+        rest: many0!(chain!(space? ~ tag!("&&") ~ space? ~ f: parse_atom, || f)),
+        || rest.into_iter().fold(first, |acc, f| Filter::And(Box::new(acc), Box::new(f)))
+    )
+);
+
+named!(parse_or<Filter>,
+    chain!(
+        first: parse_and ~
+        rest: many0!(chain!(space? ~ tag!("||") ~ space? ~ f: parse_and, || f)),
+        || rest.into_iter().fold(first, |acc, f| Filter::Or(Box::new(acc), Box::new(f)))
+    )
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(category: &str, text: &str, tags: &[&str], prefix: &str) -> SummaryElement {
+        SummaryElement {
+            prefix: prefix.to_owned(),
+            category: category.to_owned(),
+            text: text.to_owned(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            scope: "".to_owned(),
+            breaking: false,
+        }
+    }
+
+    #[test]
+    fn category_and_not_tag() {
+        let filter = Filter::parse(r#"category == "Fixed" && !tag("wip")"#).unwrap();
+        assert!(filter.matches(&item("Fixed", "fix bug", &[], "")));
+        assert!(!filter.matches(&item("Fixed", "fix bug", &["wip"], "")));
+        assert!(!filter.matches(&item("Added", "new thing", &[], "")));
+    }
+
+    #[test]
+    fn tag_or_breaking() {
+        let filter = Filter::parse(r#"tag("security") || breaking"#).unwrap();
+        assert!(filter.matches(&item("Fixed", "fix", &["security"], "")));
+        assert!(!filter.matches(&item("Fixed", "fix", &[], "")));
+    }
+
+    #[test]
+    fn prefix_regex_is_reused_across_matches() {
+        let filter = Filter::parse(r#"prefix ~ "^AB-\d+$""#).unwrap();
+        assert!(filter.matches(&item("Fixed", "fix", &[], "AB-42")));
+        assert!(!filter.matches(&item("Fixed", "fix", &[], "XY-42")));
+        // Matching twice exercises the same compiled Regex rather than recompiling it
+        assert!(filter.matches(&item("Fixed", "fix", &[], "AB-7")));
+    }
+
+    #[test]
+    fn invalid_prefix_regex_fails_to_parse() {
+        assert!(Filter::parse(r#"prefix ~ "(""#).is_err());
+    }
+}
@@ -1,18 +1,21 @@
 use nom::{IResult, alpha, digit, space, rest};
 use regex::{Regex, RegexBuilder};
-use chrono::{Date, UTC, Datelike};
+use chrono::{Date, UTC};
 use term;
 
+use std::collections::BTreeMap;
 use std::str;
 use std::fmt;
 use std::io;
 
-use config::Config;
+use config::{Config, CommitStyle};
+use renderer;
 
 #[derive(Debug)]
 pub enum Error {
     SummaryParsing(String),
     FooterParsing(String),
+    FilterParsing(String),
     CommitMessageLength,
     Terminal,
     Io(io::Error),
@@ -23,6 +26,7 @@ impl fmt::Display for Error {
         match *self {
             Error::SummaryParsing(ref line) => write!(f, "Could not parse commit summary: {}", line),
             Error::FooterParsing(ref line) => write!(f, "Could not parse commit footer: {}", line),
+            Error::FilterParsing(ref expr) => write!(f, "Could not parse filter expression: {}", expr),
             Error::CommitMessageLength => write!(f, "Commit message length too small."),
             Error::Terminal => write!(f, "Could not print to terminal."),
             Error::Io(ref e) => write!(f, "Io error: {}", e),
@@ -55,20 +59,7 @@ pub struct ParsedTag {
 
 impl Print for ParsedTag {
     fn print(&self, config: &Config) -> Result<bool, Error> {
-        let mut t = try!(term::stdout().ok_or(Error::Terminal));
-        if config.colored_output {
-            try!(t.fg(term::color::GREEN));
-        }
-        print!("\n{} ", self.name);
-        if config.colored_output {
-            try!(t.fg(term::color::YELLOW));
-        }
-        println!("({}-{:02}-{:02}):",
-                 self.date.year(),
-                 self.date.month(),
-                 self.date.day());
-        try!(t.reset());
-        Ok(true)
+        renderer::renderer_for(config).render_tag(self, config)
     }
 }
 
@@ -81,43 +72,111 @@ pub struct ParsedCommit {
 
 impl Print for ParsedCommit {
     fn print(&self, config: &Config) -> Result<bool, Error> {
-        // If summary is already filtered out than dont print at all
-        if !try!(self.summary.print(config)) {
-            return Ok(false);
+        renderer::renderer_for(config).render_commit(self, config)
+    }
+}
+
+/// A tag together with every commit made since the previous one. Printing
+/// this with `config.group_by_category` enabled buckets all summaries and
+/// categorized list entries by category instead of printing them in commit order.
+#[derive(Debug, Clone)]
+pub struct GroupedTag {
+    pub tag: ParsedTag,
+    pub commits: Vec<ParsedCommit>,
+}
+
+/// A `SummaryElement` or `ListElement` flattened into a category bucket by
+/// `GroupedTag::print`. Keeps `breaking`/`scope` alongside the text so a
+/// renderer's grouped output matches its ungrouped `render_summary` output
+/// for the same commit. List entries have no scope and are never breaking.
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub struct GroupedEntry {
+    pub prefix: String,
+    pub scope: String,
+    pub text: String,
+    pub breaking: bool,
+}
+
+/// Flattens every summary and list entry of `commits` that passes `config.filter`
+/// into `GroupedEntry` buckets keyed by category (list entries without a category
+/// are skipped), in the order `GroupedTag::print` should
+/// render them: categories ordered per `config.category_order` (ties broken
+/// alphabetically, unlisted categories appended alphabetically), then
+/// entries within a category sorted by prefix or text per `config.sort_by_prefix`.
+fn grouped_entries_by_category(commits: &[ParsedCommit], config: &Config) -> Vec<(String, Vec<GroupedEntry>)> {
+    let mut buckets: BTreeMap<String, Vec<GroupedEntry>> = BTreeMap::new();
+    for commit in commits {
+        let summary = &commit.summary;
+        if renderer::is_included(summary, config) {
+            buckets.entry(summary.category.clone())
+                .or_insert_with(Vec::new)
+                .push(GroupedEntry {
+                    prefix: summary.prefix.clone(),
+                    scope: summary.scope.clone(),
+                    text: summary.text.clone(),
+                    breaking: summary.breaking,
+                });
         }
-        let mut t = try!(term::stdout().ok_or(Error::Terminal));
-        for item in &self.body {
-            match *item {
-                BodyElement::List(ref vec) => {
-                    for item in vec {
-                        // Check if list item contains excluded tag
-                        if item.tags.iter().filter(|x| config.excluded_tags.contains(x)).count() > 0usize {
-                            continue;
-                        }
-                        print!("    - ");
-                        if !item.category.is_empty() {
-                            if config.colored_output {
-                                try!(t.fg(term::color::BRIGHT_BLUE));
-                            }
-                            print!("[{}]", item.category);
-                            if config.colored_output {
-                                try!(t.fg(term::color::WHITE));
-                            }
-                        }
-                        println!("{}", item.text);
-                    }
-                }
-                BodyElement::Paragraph(ref par) => {
-                    // Check if paragraph contains excluded tag
-                    if par.tags.iter().filter(|x| config.excluded_tags.contains(x)).count() == 0usize {
-                        for line in par.text.lines().map(|x| format!("    {}", x)).collect::<Vec<String>>() {
-                            println!("{}", line);
-                        }
+        for item in &commit.body {
+            if let BodyElement::List(ref vec) = *item {
+                for list_item in vec {
+                    if list_item.category.is_empty() || !renderer::is_included(list_item, config) {
+                        continue;
                     }
+                    buckets.entry(list_item.category.clone())
+                        .or_insert_with(Vec::new)
+                        .push(GroupedEntry {
+                            prefix: "".to_owned(),
+                            scope: "".to_owned(),
+                            text: list_item.text.clone(),
+                            breaking: false,
+                        });
                 }
             }
         }
-        try!(t.reset());
+    }
+
+    let mut categories: Vec<String> = buckets.keys().cloned().collect();
+    categories.sort_by(|a, b| {
+        let pos = |c: &str| config.category_order.iter().position(|x| x == c).unwrap_or(usize::max_value());
+        let (pos_a, pos_b) = (pos(a), pos(b));
+        if pos_a != pos_b { pos_a.cmp(&pos_b) } else { a.cmp(b) }
+    });
+
+    categories.into_iter()
+        .map(|category| {
+            let mut entries = buckets.remove(&category).unwrap_or_default();
+            if config.sort_by_prefix {
+                entries.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+            } else {
+                entries.sort_by(|a, b| a.text.cmp(&b.text));
+            }
+            (category, entries)
+        })
+        .collect()
+}
+
+impl Print for GroupedTag {
+    fn print(&self, config: &Config) -> Result<bool, Error> {
+        if !try!(self.tag.print(config)) {
+            return Ok(false);
+        }
+
+        if !config.group_by_category {
+            for commit in &self.commits {
+                try!(commit.print(config));
+            }
+            return Ok(true);
+        }
+
+        let renderer = renderer::renderer_for(config);
+        for (category, entries) in grouped_entries_by_category(&self.commits, config) {
+            try!(renderer.render_category_heading(&category));
+            for entry in &entries {
+                try!(renderer.render_grouped_entry(entry, config));
+            }
+        }
+
         Ok(true)
     }
 }
@@ -128,29 +187,18 @@ pub struct SummaryElement {
     pub category: String,
     pub text: String,
     pub tags: Vec<String>,
+    /// The Conventional Commits scope, e.g. `parser` in `feat(parser): ...`.
+    /// Always empty under `CommitStyle::GitJournal`.
+    pub scope: String,
+    /// Set when the commit is marked as a breaking change, either via a
+    /// trailing `!` before the summary colon or a `BREAKING CHANGE`/
+    /// `BREAKING-CHANGE` footer. Only ever `true` under `CommitStyle::Conventional`.
+    pub breaking: bool,
 }
 
 impl Print for SummaryElement {
     fn print(&self, config: &Config) -> Result<bool, Error> {
-        // Filter out excluded tags
-        if self.tags.iter().filter(|x| config.excluded_tags.contains(x)).count() > 0usize {
-            return Ok(false);
-        }
-        let mut t = try!(term::stdout().ok_or(Error::Terminal));
-        print!("- ");
-        if config.show_prefix && !self.prefix.is_empty() {
-            print!("{} ", self.prefix);
-        }
-        if config.colored_output {
-            try!(t.fg(term::color::BRIGHT_BLUE));
-        }
-        print!("[{}]", self.category);
-        if config.colored_output {
-            try!(t.fg(term::color::WHITE));
-        }
-        println!("{}", self.text);
-        try!(t.reset());
-        Ok(true)
+        renderer::renderer_for(config).render_summary(self, config)
     }
 }
 
@@ -158,6 +206,11 @@ impl Print for SummaryElement {
 pub enum BodyElement {
     List(Vec<ListElement>),
     Paragraph(ParagraphElement),
+    /// A fenced ``` code block, captured verbatim: no tag stripping, no re-indentation
+    Code {
+        language: String,
+        text: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
@@ -181,14 +234,19 @@ pub struct FooterElement {
 
 lazy_static! {
     static ref RE_TAGS: Regex = Regex::new(r" :(.*?):").unwrap();
-    static ref RE_FOOTER: Regex = RegexBuilder::new(r"^([\w-]+):\s(.*)$").multi_line(true).compile().unwrap();
+    static ref RE_FOOTER: Regex =
+        RegexBuilder::new(r"^([\w-]+|BREAKING CHANGE):\s(.*)$").multi_line(true).compile().unwrap();
     static ref RE_LIST: Regex = RegexBuilder::new(r"^-\s.*$(\n^\s+-\s.*)*").multi_line(true).compile().unwrap();
+    static ref RE_CONVENTIONAL: Regex =
+        Regex::new(r"^(?P<type>[[:alpha:]-]+)(\((?P<scope>[^()]+)\))?(?P<breaking>!)?:\s*(?P<subject>.*)$").unwrap();
+    static ref RE_CODE_BLOCK: Regex =
+        RegexBuilder::new(r"^```(?P<language>[\w+-]*)\n(?P<code>[\s\S]*?)\n```$").multi_line(true).compile().unwrap();
 }
 
 pub struct Parser;
 impl Parser {
     /// Parses a single commit message and returns a changelog ready form
-    pub fn parse_commit_message(&self, message: &str) -> Result<ParsedCommit, Error> {
+    pub fn parse_commit_message(&self, message: &str, config: &Config) -> Result<ParsedCommit, Error> {
 
         /// Parses for tags and returns them with the resulting string
         fn parse_and_consume_tags(input: &[u8]) -> (Vec<String>, String) {
@@ -232,11 +290,32 @@ impl Parser {
             )
         );
 
-        // Every block is split by two newlines
-        let mut commit_parts = message.split("\n\n");
+        // The summary is separated from the body/footer by the first blank line. The
+        // body is kept as one string (rather than eagerly splitting every block) so a
+        // fenced code block can span blank lines without being torn apart below.
+        let mut message_parts = message.splitn(2, "\n\n");
+
+        /// Parses a Conventional Commits style summary, e.g. `feat(scope)!: subject`
+        fn parse_conventional_summary(summary_line: &str, config: &Config) -> Result<SummaryElement, Error> {
+            let caps = try!(RE_CONVENTIONAL.captures(summary_line)
+                .ok_or_else(|| Error::SummaryParsing(summary_line.to_owned())));
+            let commit_type = caps.name("type").unwrap_or("");
+            let (tags, text) = parse_and_consume_tags(caps.name("subject").unwrap_or("").as_bytes());
+            Ok(SummaryElement {
+                prefix: "".to_owned(),
+                category: config.conventional_commit_types
+                    .get(commit_type)
+                    .cloned()
+                    .unwrap_or_else(|| commit_type.to_owned()),
+                text: text,
+                tags: tags,
+                scope: caps.name("scope").unwrap_or("").to_owned(),
+                breaking: caps.name("breaking").is_some(),
+            })
+        }
 
         // Parse the summary line
-        let summary_line = try!(commit_parts.nth(0).ok_or(Error::CommitMessageLength)).trim();
+        let summary_line = try!(message_parts.next().ok_or(Error::CommitMessageLength)).trim();
         named!(parse_summary<SummaryElement>,
             chain!(
                 p_prefix: separated_pair!(alpha, char!('-'), digit)? ~
@@ -251,18 +330,23 @@ impl Parser {
                 category: p_category.to_owned(),
                 tags: p_tags_rest.0.clone(),
                 text: p_tags_rest.1.clone(),
+                scope: "".to_owned(),
+                breaking: false,
             })
         );
-        let parsed_summary = match parse_summary(summary_line.as_bytes()) {
-            IResult::Done(_, parsed) => parsed,
-            _ => return Err(Error::SummaryParsing(summary_line.to_owned())),
+        let mut parsed_summary = match config.commit_style {
+            CommitStyle::Conventional => try!(parse_conventional_summary(summary_line, config)),
+            CommitStyle::GitJournal => {
+                match parse_summary(summary_line.as_bytes()) {
+                    IResult::Done(_, parsed) => parsed,
+                    _ => return Err(Error::SummaryParsing(summary_line.to_owned())),
+                }
+            }
         };
 
-        // Parse the body and the footer, the summary is already consumed
-        let mut parsed_footer = vec![];
-        let mut parsed_body = vec![];
-        for part in commit_parts {
-            // Parse footer
+        /// Parses a single blank-line-separated block into a footer, list or paragraph
+        fn parse_text_block(part: &str, parsed_footer: &mut Vec<FooterElement>, parsed_body: &mut Vec<BodyElement>)
+                             -> Result<(), Error> {
             if RE_FOOTER.is_match(part) {
                 for cap in RE_FOOTER.captures_iter(part) {
                     parsed_footer.push(FooterElement {
@@ -287,6 +371,46 @@ impl Parser {
                     tags: parsed_tags,
                 }));
             }
+            Ok(())
+        }
+
+        /// Splits `text` on blank lines and feeds every non-empty block through `parse_text_block`
+        fn parse_text_blocks(text: &str, parsed_footer: &mut Vec<FooterElement>, parsed_body: &mut Vec<BodyElement>)
+                              -> Result<(), Error> {
+            for part in text.split("\n\n") {
+                let part = part.trim_matches('\n');
+                if !part.trim().is_empty() {
+                    try!(parse_text_block(part, parsed_footer, parsed_body));
+                }
+            }
+            Ok(())
+        }
+
+        // Parse the body and the footer, the summary is already consumed. Fenced code
+        // blocks are pulled out first and kept verbatim (no tag stripping, no
+        // re-indentation); everything in between is split on blank lines as before.
+        let body_text = message_parts.next().unwrap_or("");
+        let mut parsed_footer = vec![];
+        let mut parsed_body = vec![];
+        let mut last_end = 0usize;
+        for cap in RE_CODE_BLOCK.captures_iter(body_text) {
+            if let Some((start, end)) = cap.pos(0) {
+                try!(parse_text_blocks(&body_text[last_end..start], &mut parsed_footer, &mut parsed_body));
+                parsed_body.push(BodyElement::Code {
+                    language: cap.name("language").unwrap_or("").to_owned(),
+                    text: cap.name("code").unwrap_or("").to_owned(),
+                });
+                last_end = end;
+            }
+        }
+        try!(parse_text_blocks(&body_text[last_end..], &mut parsed_footer, &mut parsed_body));
+
+        // A `BREAKING CHANGE`/`BREAKING-CHANGE` footer also marks the commit as breaking.
+        // Only Conventional Commits defines this footer, so leave `breaking` alone under
+        // CommitStyle::GitJournal, matching SummaryElement::breaking's doc comment.
+        if config.commit_style == CommitStyle::Conventional &&
+           parsed_footer.iter().any(|f| f.key == "BREAKING CHANGE" || f.key == "BREAKING-CHANGE") {
+            parsed_summary.breaking = true;
         }
 
         Ok(ParsedCommit {
@@ -295,4 +419,118 @@ impl Parser {
             footer: parsed_footer,
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{Config, CommitStyle};
+
+    #[test]
+    fn breaking_change_footer_space_form_marks_commit_breaking() {
+        let config = Config { commit_style: CommitStyle::Conventional, ..Config::default() };
+        let message = "feat: add widget\n\nBREAKING CHANGE: widgets are now required";
+        let commit = Parser.parse_commit_message(message, &config).unwrap();
+        assert!(commit.summary.breaking);
+        assert_eq!(commit.footer[0].key, "BREAKING CHANGE");
+    }
+
+    #[test]
+    fn breaking_change_footer_hyphen_form_marks_commit_breaking() {
+        let config = Config { commit_style: CommitStyle::Conventional, ..Config::default() };
+        let message = "feat: add widget\n\nBREAKING-CHANGE: widgets are now required";
+        let commit = Parser.parse_commit_message(message, &config).unwrap();
+        assert!(commit.summary.breaking);
+    }
+
+    #[test]
+    fn breaking_change_footer_ignored_under_git_journal_style() {
+        let config = Config { commit_style: CommitStyle::GitJournal, ..Config::default() };
+        let message = "[Added] widget\n\nBREAKING CHANGE: widgets are now required";
+        let commit = Parser.parse_commit_message(message, &config).unwrap();
+        assert!(!commit.summary.breaking);
+    }
+
+    #[test]
+    fn fenced_code_block_is_preserved_verbatim() {
+        let config = Config::default();
+        let message = "[Added] widget\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```";
+        let commit = Parser.parse_commit_message(message, &config).unwrap();
+        match commit.body[0] {
+            BodyElement::Code { ref language, ref text } => {
+                assert_eq!(language, "rust");
+                assert_eq!(text, "fn main() {\n    println!(\"hi\");\n}");
+            }
+            _ => panic!("expected a BodyElement::Code"),
+        }
+    }
+
+    #[test]
+    fn fenced_code_block_spanning_blank_lines_is_not_split() {
+        let config = Config::default();
+        let message = "[Added] widget\n\n```py\ndef f():\n\n    pass\n```\n\nsome trailing paragraph";
+        let commit = Parser.parse_commit_message(message, &config).unwrap();
+        assert_eq!(commit.body.len(), 2);
+        match commit.body[0] {
+            BodyElement::Code { ref language, ref text } => {
+                assert_eq!(language, "py");
+                assert_eq!(text, "def f():\n\n    pass");
+            }
+            _ => panic!("expected a BodyElement::Code"),
+        }
+        match commit.body[1] {
+            BodyElement::Paragraph(ref par) => assert_eq!(par.text, "some trailing paragraph"),
+            _ => panic!("expected a BodyElement::Paragraph"),
+        }
+    }
+
+    fn summary_commit(category: &str, prefix: &str, text: &str, breaking: bool) -> ParsedCommit {
+        ParsedCommit {
+            summary: SummaryElement {
+                prefix: prefix.to_owned(),
+                category: category.to_owned(),
+                text: text.to_owned(),
+                tags: vec![],
+                scope: "".to_owned(),
+                breaking: breaking,
+            },
+            body: vec![],
+            footer: vec![],
+        }
+    }
+
+    #[test]
+    fn grouped_entries_use_category_order_then_alphabetical_fallback() {
+        let config = Config { category_order: vec!["Fixed".to_owned(), "Added".to_owned()], ..Config::default() };
+        let commits = vec![summary_commit("Added", "A-1", "add thing", false),
+                           summary_commit("Zeta", "Z-1", "zeta thing", false),
+                           summary_commit("Fixed", "F-1", "fix thing", false)];
+        let grouped = grouped_entries_by_category(&commits, &config);
+        let categories: Vec<&str> = grouped.iter().map(|t| t.0.as_str()).collect();
+        // "Fixed"/"Added" follow category_order; "Zeta" isn't listed so falls back after them, alphabetically
+        assert_eq!(categories, vec!["Fixed", "Added", "Zeta"]);
+    }
+
+    #[test]
+    fn grouped_entries_carry_breaking_flag() {
+        let config = Config::default();
+        let commits = vec![summary_commit("Added", "A-1", "add thing", true)];
+        let grouped = grouped_entries_by_category(&commits, &config);
+        assert!(grouped[0].1[0].breaking);
+    }
+
+    #[test]
+    fn grouped_entries_sort_by_text_unless_sort_by_prefix() {
+        let commits = vec![summary_commit("Added", "B-2", "bravo", false),
+                            summary_commit("Added", "A-1", "alpha", false)];
+
+        let by_text = grouped_entries_by_category(&commits, &Config::default());
+        let texts: Vec<&str> = by_text[0].1.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["alpha", "bravo"]);
+
+        let config = Config { sort_by_prefix: true, ..Config::default() };
+        let by_prefix = grouped_entries_by_category(&commits, &config);
+        let prefixes: Vec<&str> = by_prefix[0].1.iter().map(|e| e.prefix.as_str()).collect();
+        assert_eq!(prefixes, vec!["A-1", "B-2"]);
+    }
 }
\ No newline at end of file
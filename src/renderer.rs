@@ -0,0 +1,507 @@
+use chrono::Datelike;
+use term;
+
+use config::{Config, OutputFormat};
+use filter::Filterable;
+use parser::{BodyElement, Error, FooterElement, GroupedEntry, ListElement, ParagraphElement, ParsedCommit,
+             ParsedTag, SummaryElement};
+
+/// Renders parsed tags/commits to some output format. Implementors own the
+/// entire side effect of printing; the `bool` they return mirrors `Print`'s,
+/// i.e. whether anything was printed after tag/category filtering.
+pub trait Renderer {
+    fn render_tag(&self, tag: &ParsedTag, config: &Config) -> Result<bool, Error>;
+    fn render_commit(&self, commit: &ParsedCommit, config: &Config) -> Result<bool, Error>;
+    fn render_summary(&self, summary: &SummaryElement, config: &Config) -> Result<bool, Error>;
+
+    /// Prints the heading of a category section, used when
+    /// `config.group_by_category` is enabled
+    fn render_category_heading(&self, category: &str) -> Result<(), Error>;
+
+    /// Prints a single grouped entry (a flattened `SummaryElement` or
+    /// `ListElement`) underneath a category heading
+    fn render_grouped_entry(&self, entry: &GroupedEntry, config: &Config) -> Result<bool, Error>;
+}
+
+/// Returns the `Renderer` selected by `config.output_format`
+pub fn renderer_for(config: &Config) -> Box<Renderer> {
+    match config.output_format {
+        OutputFormat::Terminal => Box::new(TerminalRenderer),
+        OutputFormat::Markdown => Box::new(MarkdownRenderer),
+        OutputFormat::Json => Box::new(JsonRenderer),
+    }
+}
+
+/// Whether an entry should be printed, per `config.filter`. A `None` filter
+/// prints everything. `pub(crate)` so callers outside this module (e.g.
+/// `parser::grouped_entries_by_category`) consult the same predicate instead of re-implementing it.
+pub(crate) fn is_included<T: Filterable>(item: &T, config: &Config) -> bool {
+    config.filter.as_ref().map_or(true, |f| f.matches(item))
+}
+
+/// A handful of keywords per language, as a lightweight stand-in for a full
+/// syntect-style grammar. Unrecognized languages are printed without highlighting.
+fn keywords_for(language: &str) -> &'static [&'static str] {
+    match language {
+        "rust" | "rs" => {
+            &["fn", "let", "mut", "pub", "struct", "enum", "impl", "trait", "match", "if", "else", "for", "while",
+              "return", "use"]
+        }
+        "python" | "py" => {
+            &["def", "class", "import", "from", "if", "elif", "else", "for", "while", "return", "try", "except"]
+        }
+        "javascript" | "js" | "typescript" | "ts" => {
+            &["function", "const", "let", "var", "if", "else", "for", "while", "return", "class", "import", "export"]
+        }
+        _ => &[],
+    }
+}
+
+/// Prints one line of a fenced code block, highlighting recognized keywords in
+/// `BRIGHT_MAGENTA` when `colored` is set and `language` is recognized.
+fn print_highlighted_line(t: &mut Box<term::StdoutTerminal>, language: &str, line: &str, colored: bool)
+                           -> Result<(), Error> {
+    let keywords = keywords_for(language);
+    if !colored || keywords.is_empty() {
+        println!("{}", line);
+        return Ok(());
+    }
+    let mut words = line.split(' ').peekable();
+    while let Some(word) = words.next() {
+        if keywords.contains(&word) {
+            try!(t.fg(term::color::BRIGHT_MAGENTA));
+            print!("{}", word);
+            try!(t.fg(term::color::WHITE));
+        } else {
+            print!("{}", word);
+        }
+        if words.peek().is_some() {
+            print!(" ");
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// The original colored terminal output
+pub struct TerminalRenderer;
+
+impl Renderer for TerminalRenderer {
+    fn render_tag(&self, tag: &ParsedTag, config: &Config) -> Result<bool, Error> {
+        let mut t = try!(term::stdout().ok_or(Error::Terminal));
+        if config.colored_output {
+            try!(t.fg(term::color::GREEN));
+        }
+        print!("\n{} ", tag.name);
+        if config.colored_output {
+            try!(t.fg(term::color::YELLOW));
+        }
+        println!("({}-{:02}-{:02}):", tag.date.year(), tag.date.month(), tag.date.day());
+        try!(t.reset());
+        Ok(true)
+    }
+
+    fn render_commit(&self, commit: &ParsedCommit, config: &Config) -> Result<bool, Error> {
+        // If summary is already filtered out than dont print at all
+        if !try!(self.render_summary(&commit.summary, config)) {
+            return Ok(false);
+        }
+        let mut t = try!(term::stdout().ok_or(Error::Terminal));
+        for item in &commit.body {
+            match *item {
+                BodyElement::List(ref vec) => {
+                    for item in vec {
+                        // Check if list item is selected by the filter
+                        if !is_included(item, config) {
+                            continue;
+                        }
+                        print!("    - ");
+                        if !item.category.is_empty() {
+                            if config.colored_output {
+                                try!(t.fg(term::color::BRIGHT_BLUE));
+                            }
+                            print!("[{}]", item.category);
+                            if config.colored_output {
+                                try!(t.fg(term::color::WHITE));
+                            }
+                        }
+                        println!("{}", item.text);
+                    }
+                }
+                BodyElement::Paragraph(ref par) => {
+                    // Check if paragraph is selected by the filter
+                    if is_included(par, config) {
+                        for line in par.text.lines().map(|x| format!("    {}", x)).collect::<Vec<String>>() {
+                            println!("{}", line);
+                        }
+                    }
+                }
+                BodyElement::Code { ref language, ref text } => {
+                    for line in text.lines() {
+                        try!(print_highlighted_line(&mut t, language, line, config.colored_output));
+                    }
+                }
+            }
+        }
+        try!(t.reset());
+        Ok(true)
+    }
+
+    fn render_summary(&self, summary: &SummaryElement, config: &Config) -> Result<bool, Error> {
+        if !is_included(summary, config) {
+            return Ok(false);
+        }
+        let mut t = try!(term::stdout().ok_or(Error::Terminal));
+        print!("- ");
+        if config.show_prefix && !summary.prefix.is_empty() {
+            print!("{} ", summary.prefix);
+        }
+        if summary.breaking {
+            if config.colored_output {
+                try!(t.fg(term::color::BRIGHT_RED));
+            }
+            print!("[BREAKING]");
+            if config.colored_output {
+                try!(t.fg(term::color::WHITE));
+            }
+            print!(" ");
+        }
+        if config.colored_output {
+            try!(t.fg(term::color::BRIGHT_BLUE));
+        }
+        print!("[{}]", summary.category);
+        if config.colored_output {
+            try!(t.fg(term::color::WHITE));
+        }
+        println!("{}", summary.text);
+        try!(t.reset());
+        Ok(true)
+    }
+
+    fn render_category_heading(&self, category: &str) -> Result<(), Error> {
+        let mut t = try!(term::stdout().ok_or(Error::Terminal));
+        try!(t.fg(term::color::BRIGHT_BLUE));
+        println!("\n  {}:", category);
+        try!(t.reset());
+        Ok(())
+    }
+
+    fn render_grouped_entry(&self, entry: &GroupedEntry, config: &Config) -> Result<bool, Error> {
+        let mut t = try!(term::stdout().ok_or(Error::Terminal));
+        print!("    - ");
+        if config.show_prefix && !entry.prefix.is_empty() {
+            print!("{} ", entry.prefix);
+        }
+        if entry.breaking {
+            if config.colored_output {
+                try!(t.fg(term::color::BRIGHT_RED));
+            }
+            print!("[BREAKING]");
+            if config.colored_output {
+                try!(t.fg(term::color::WHITE));
+            }
+            print!(" ");
+        }
+        println!("{}", entry.text);
+        try!(t.reset());
+        Ok(true)
+    }
+}
+
+/// Markdown output, suitable for committing as `CHANGELOG.md`
+pub struct MarkdownRenderer;
+
+/// Builds the Markdown line for a summary, e.g. `- AB-1 **BREAKING** **[Fixed]** text`
+fn render_summary_markdown(summary: &SummaryElement, config: &Config) -> String {
+    let mut line = "- ".to_owned();
+    if config.show_prefix && !summary.prefix.is_empty() {
+        line.push_str(&summary.prefix);
+        line.push(' ');
+    }
+    if summary.breaking {
+        line.push_str("**BREAKING** ");
+    }
+    line.push_str(&format!("**[{}]** {}", summary.category, summary.text));
+    line
+}
+
+/// Builds the Markdown line for a list item, e.g. `    - **[Fixed]** text`
+fn render_list_item_markdown(item: &ListElement) -> String {
+    if item.category.is_empty() {
+        format!("    - {}", item.text)
+    } else {
+        format!("    - **[{}]** {}", item.category, item.text)
+    }
+}
+
+impl MarkdownRenderer {
+    fn render_footer(&self, footer: &[FooterElement]) {
+        if footer.is_empty() {
+            return;
+        }
+        println!();
+        for item in footer {
+            println!("    - **{}**: {}", item.key, item.value);
+        }
+    }
+}
+
+impl Renderer for MarkdownRenderer {
+    fn render_tag(&self, tag: &ParsedTag, _: &Config) -> Result<bool, Error> {
+        println!("\n## {} ({}-{:02}-{:02})", tag.name, tag.date.year(), tag.date.month(), tag.date.day());
+        Ok(true)
+    }
+
+    fn render_commit(&self, commit: &ParsedCommit, config: &Config) -> Result<bool, Error> {
+        if !try!(self.render_summary(&commit.summary, config)) {
+            return Ok(false);
+        }
+        for item in &commit.body {
+            match *item {
+                BodyElement::List(ref vec) => {
+                    for item in vec {
+                        if !is_included(item, config) {
+                            continue;
+                        }
+                        println!("{}", render_list_item_markdown(item));
+                    }
+                }
+                BodyElement::Paragraph(ref par) => {
+                    if is_included(par, config) {
+                        for line in par.text.lines() {
+                            println!("    {}", line);
+                        }
+                    }
+                }
+                BodyElement::Code { ref language, ref text } => {
+                    println!("```{}", language);
+                    println!("{}", text);
+                    println!("```");
+                }
+            }
+        }
+        self.render_footer(&commit.footer);
+        Ok(true)
+    }
+
+    fn render_summary(&self, summary: &SummaryElement, config: &Config) -> Result<bool, Error> {
+        if !is_included(summary, config) {
+            return Ok(false);
+        }
+        println!("{}", render_summary_markdown(summary, config));
+        Ok(true)
+    }
+
+    fn render_category_heading(&self, category: &str) -> Result<(), Error> {
+        println!("\n### {}", category);
+        Ok(())
+    }
+
+    fn render_grouped_entry(&self, entry: &GroupedEntry, config: &Config) -> Result<bool, Error> {
+        print!("- ");
+        if config.show_prefix && !entry.prefix.is_empty() {
+            print!("{} ", entry.prefix);
+        }
+        if entry.breaking {
+            print!("**BREAKING** ");
+        }
+        println!("{}", entry.text);
+        Ok(true)
+    }
+}
+
+/// JSON output, for downstream tooling/CI
+pub struct JsonRenderer;
+
+/// Escapes a string for embedding in a JSON string literal: `\` and `"`, the
+/// named control-character escapes (`\n`, `\r`, `\t`), and every other
+/// control character (U+0000-U+001F) as a generic `\u00XX` sequence. A pasted code
+/// block or CRLF commit message can easily contain tabs/`\r`, and leaving
+/// those raw produces JSON a real parser (serde_json, jq, ...) rejects.
+fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_list_item_json(item: &ListElement) -> String {
+    format!("{{\"category\":\"{}\",\"text\":\"{}\",\"tags\":[{}]}}",
+            escape_json(&item.category),
+            escape_json(&item.text),
+            render_tags_json(&item.tags))
+}
+
+fn render_paragraph_json(par: &ParagraphElement) -> String {
+    format!("{{\"text\":\"{}\",\"tags\":[{}]}}", escape_json(&par.text), render_tags_json(&par.tags))
+}
+
+fn render_footer_json(footer: &FooterElement) -> String {
+    format!("{{\"key\":\"{}\",\"value\":\"{}\"}}", escape_json(&footer.key), escape_json(&footer.value))
+}
+
+fn render_tags_json(tags: &[String]) -> String {
+    tags.iter().map(|t| format!("\"{}\"", escape_json(t))).collect::<Vec<String>>().join(",")
+}
+
+fn render_summary_json(summary: &SummaryElement) -> String {
+    format!("{{\"prefix\":\"{}\",\"category\":\"{}\",\"scope\":\"{}\",\"text\":\"{}\",\"breaking\":{},\"tags\":[{}]}}",
+            escape_json(&summary.prefix),
+            escape_json(&summary.category),
+            escape_json(&summary.scope),
+            escape_json(&summary.text),
+            summary.breaking,
+            render_tags_json(&summary.tags))
+}
+
+impl Renderer for JsonRenderer {
+    fn render_tag(&self, tag: &ParsedTag, _: &Config) -> Result<bool, Error> {
+        println!("{{\"name\":\"{}\",\"date\":\"{}-{:02}-{:02}\"}}",
+                 escape_json(&tag.name),
+                 tag.date.year(),
+                 tag.date.month(),
+                 tag.date.day());
+        Ok(true)
+    }
+
+    fn render_commit(&self, commit: &ParsedCommit, config: &Config) -> Result<bool, Error> {
+        if !is_included(commit, config) {
+            return Ok(false);
+        }
+        let body = commit.body
+            .iter()
+            .map(|item| match *item {
+                BodyElement::List(ref vec) => {
+                    format!("{{\"list\":[{}]}}",
+                            vec.iter()
+                                .filter(|item| is_included(*item, config))
+                                .map(render_list_item_json)
+                                .collect::<Vec<String>>()
+                                .join(","))
+                }
+                BodyElement::Paragraph(ref par) => {
+                    if is_included(par, config) {
+                        format!("{{\"paragraph\":{}}}", render_paragraph_json(par))
+                    } else {
+                        "null".to_owned()
+                    }
+                }
+                BodyElement::Code { ref language, ref text } => {
+                    format!("{{\"code\":{{\"language\":\"{}\",\"text\":\"{}\"}}}}",
+                            escape_json(language),
+                            escape_json(text))
+                }
+            })
+            .filter(|item| item.as_str() != "null")
+            .collect::<Vec<String>>()
+            .join(",");
+        let footer = commit.footer.iter().map(render_footer_json).collect::<Vec<String>>().join(",");
+        println!("{{\"summary\":{},\"body\":[{}],\"footer\":[{}]}}",
+                 render_summary_json(&commit.summary),
+                 body,
+                 footer);
+        Ok(true)
+    }
+
+    fn render_summary(&self, summary: &SummaryElement, config: &Config) -> Result<bool, Error> {
+        if !is_included(summary, config) {
+            return Ok(false);
+        }
+        println!("{}", render_summary_json(summary));
+        Ok(true)
+    }
+
+    fn render_category_heading(&self, category: &str) -> Result<(), Error> {
+        println!("{{\"category\":\"{}\"}}", escape_json(category));
+        Ok(())
+    }
+
+    fn render_grouped_entry(&self, entry: &GroupedEntry, _: &Config) -> Result<bool, Error> {
+        println!("{{\"prefix\":\"{}\",\"scope\":\"{}\",\"text\":\"{}\",\"breaking\":{}}}",
+                 escape_json(&entry.prefix),
+                 escape_json(&entry.scope),
+                 escape_json(&entry.text),
+                 entry.breaking);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaking_summary() -> SummaryElement {
+        SummaryElement {
+            prefix: "AB-1".to_owned(),
+            category: "Fixed".to_owned(),
+            text: "fix widget".to_owned(),
+            tags: vec!["security".to_owned()],
+            scope: "widget".to_owned(),
+            breaking: true,
+        }
+    }
+
+    #[test]
+    fn escape_json_escapes_all_control_characters() {
+        assert_eq!(escape_json("a\\b\"c\nd\re\tf\u{1}"), "a\\\\b\\\"c\\nd\\re\\tf\\u0001");
+    }
+
+    #[test]
+    fn json_summary_includes_breaking_and_tags() {
+        let json = render_summary_json(&breaking_summary());
+        assert_eq!(json,
+                   "{\"prefix\":\"AB-1\",\"category\":\"Fixed\",\"scope\":\"widget\",\"text\":\"fix widget\",\
+                    \"breaking\":true,\"tags\":[\"security\"]}");
+    }
+
+    #[test]
+    fn json_list_item_shape() {
+        let item = ListElement {
+            category: "Added".to_owned(),
+            text: "add thing".to_owned(),
+            tags: vec![],
+        };
+        assert_eq!(render_list_item_json(&item), "{\"category\":\"Added\",\"text\":\"add thing\",\"tags\":[]}");
+    }
+
+    #[test]
+    fn json_paragraph_shape() {
+        let par = ParagraphElement { text: "some notes".to_owned(), tags: vec![] };
+        assert_eq!(render_paragraph_json(&par), "{\"text\":\"some notes\",\"tags\":[]}");
+    }
+
+    #[test]
+    fn markdown_summary_shows_breaking_marker() {
+        let line = render_summary_markdown(&breaking_summary(), &Config::default());
+        assert_eq!(line, "- **BREAKING** **[Fixed]** fix widget");
+    }
+
+    #[test]
+    fn markdown_summary_shows_prefix_when_enabled() {
+        let config = Config { show_prefix: true, ..Config::default() };
+        let line = render_summary_markdown(&breaking_summary(), &config);
+        assert_eq!(line, "- AB-1 **BREAKING** **[Fixed]** fix widget");
+    }
+
+    #[test]
+    fn markdown_list_item_shape() {
+        let item = ListElement {
+            category: "Added".to_owned(),
+            text: "add thing".to_owned(),
+            tags: vec![],
+        };
+        assert_eq!(render_list_item_markdown(&item), "    - **[Added]** add thing");
+
+        let no_category = ListElement { category: "".to_owned(), text: "plain bullet".to_owned(), tags: vec![] };
+        assert_eq!(render_list_item_markdown(&no_category), "    - plain bullet");
+    }
+}